@@ -4,12 +4,14 @@ use rustc_hash::FxHashMap;
 use ra_syntax::{SmolStr, ast::AttrsOwner};
 
 use crate::{
-    Crate, DefDatabase, Enum, Function, HirDatabase, ImplBlock, Module, Static, Struct, Trait
+    Crate, DefDatabase, Enum, EnumVariant, Function, HirDatabase, ImplBlock, Module, ModuleDef,
+    Static, Struct, Trait
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum LangItemTarget {
     Enum(Enum),
+    EnumVariant(EnumVariant),
     Function(Function),
     ImplBlock(ImplBlock),
     Static(Static),
@@ -21,6 +23,7 @@ impl LangItemTarget {
     pub(crate) fn krate(&self, db: &impl HirDatabase) -> Option<Crate> {
         match self {
             LangItemTarget::Enum(e) => e.module(db).krate(db),
+            LangItemTarget::EnumVariant(v) => v.parent_enum(db).module(db).krate(db),
             LangItemTarget::Function(f) => f.module(db).krate(db),
             LangItemTarget::ImplBlock(i) => i.module().krate(db),
             LangItemTarget::Static(s) => s.module(db).krate(db),
@@ -28,6 +31,55 @@ impl LangItemTarget {
             LangItemTarget::Trait(t) => t.module(db).krate(db),
         }
     }
+
+    pub fn as_trait(self) -> Option<Trait> {
+        match self {
+            LangItemTarget::Trait(t) => Some(t),
+            _ => None,
+        }
+    }
+
+    pub fn as_function(self) -> Option<Function> {
+        match self {
+            LangItemTarget::Function(f) => Some(f),
+            _ => None,
+        }
+    }
+
+    pub fn as_struct(self) -> Option<Struct> {
+        match self {
+            LangItemTarget::Struct(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_enum(self) -> Option<Enum> {
+        match self {
+            LangItemTarget::Enum(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    pub fn as_enum_variant(self) -> Option<EnumVariant> {
+        match self {
+            LangItemTarget::EnumVariant(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_static(self) -> Option<Static> {
+        match self {
+            LangItemTarget::Static(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_impl_block(self) -> Option<ImplBlock> {
+        match self {
+            LangItemTarget::ImplBlock(i) => Some(i),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -57,25 +109,70 @@ impl LangItems {
         let source = module.definition_source(db).1;
         for (impl_id, _) in impl_blocks.impls.iter() {
             let impl_block = source_map.get(&source, impl_id);
-            let lang_item_name = impl_block
-                .attrs()
-                .filter_map(|a| a.as_key_value())
-                .filter(|(key, _)| key == "lang")
-                .map(|(_, val)| val)
-                .nth(0);
-            if let Some(lang_item_name) = lang_item_name {
+            if let Some(lang_item_name) = Self::lang_item_name(&impl_block) {
                 let imp = ImplBlock::from_id(*module, impl_id);
                 self.items.entry(lang_item_name).or_insert(LangItemTarget::ImplBlock(imp));
             }
         }
 
-        // FIXME we should look for the other lang item targets (traits, structs, ...)
+        // Look for the other lang item targets declared in this module
+        for decl in module.declarations(db) {
+            let target = match decl {
+                ModuleDef::Trait(trait_) => Self::lang_item_name(&trait_.source(db).1)
+                    .map(|name| (name, LangItemTarget::Trait(trait_))),
+                ModuleDef::Struct(strukt) => Self::lang_item_name(&strukt.source(db).1)
+                    .map(|name| (name, LangItemTarget::Struct(strukt))),
+                ModuleDef::Enum(enum_) => {
+                    for variant in enum_.variants(db) {
+                        if let Some(name) = Self::lang_item_name(&variant.source(db).1) {
+                            self.items
+                                .entry(name)
+                                .or_insert(LangItemTarget::EnumVariant(variant));
+                        }
+                    }
+                    Self::lang_item_name(&enum_.source(db).1)
+                        .map(|name| (name, LangItemTarget::Enum(enum_)))
+                }
+                ModuleDef::Function(f) => Self::lang_item_name(&f.source(db).1)
+                    .map(|name| (name, LangItemTarget::Function(f))),
+                ModuleDef::Static(s) => Self::lang_item_name(&s.source(db).1)
+                    .map(|name| (name, LangItemTarget::Static(s))),
+                _ => None,
+            };
+            if let Some((lang_item_name, target)) = target {
+                self.items.entry(lang_item_name).or_insert(target);
+            }
+        }
 
         // Look for lang items in the children
         for child in module.children(db) {
             self.collect_lang_items_recursive(db, &child);
         }
     }
+
+    /// Extracts the `#[lang = "..."]` name from an AST node's attributes, if present.
+    fn lang_item_name<N: AttrsOwner>(node: &N) -> Option<SmolStr> {
+        node.attrs()
+            .filter_map(|a| a.as_key_value())
+            .filter(|(key, _)| key == "lang")
+            .map(|(_, val)| val)
+            .nth(0)
+    }
+}
+
+/// Salsa query. Looks for a lang item, starting from the specified crate and recursively
+/// traversing its dependencies, memoizing the transitive result per `(krate, item)` so it does
+/// not need to be recomputed on every lookup.
+pub(crate) fn lang_item_query(
+    db: &impl DefDatabase,
+    start_krate: Crate,
+    item: SmolStr,
+) -> Option<LangItemTarget> {
+    let lang_items = db.lang_items(start_krate);
+    if let Some(target) = lang_items.target(&item) {
+        return Some(*target);
+    }
+    start_krate.dependencies(db).into_iter().find_map(|dep| db.lang_item(dep.krate, item.clone()))
 }
 
 /// Look for a lang item, starting from the specified crate and recursively traversing its
@@ -85,18 +182,134 @@ pub(crate) fn lang_item_lookup(
     start_krate: Crate,
     item: &str,
 ) -> Option<LangItemTarget> {
-    let lang_items = db.lang_items(start_krate);
-    let start_krate_target = lang_items.items.get(item);
-    if start_krate_target.is_some() {
-        start_krate_target.map(|t| *t)
-    } else {
-        for dep in start_krate.dependencies(db) {
-            let dep_krate = dep.krate;
-            let dep_target = lang_item_lookup(db, dep_krate, item);
-            if dep_target.is_some() {
-                return dep_target;
+    db.lang_item(start_krate, item.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mock::MockDatabase;
+
+    use super::*;
+
+    fn collect(ra_fixture: &str) -> Arc<LangItems> {
+        let (db, _file_id) = MockDatabase::with_single_file(ra_fixture);
+        let krate = db.crate_graph().iter().next().unwrap();
+        db.lang_items(Crate { crate_id: krate })
+    }
+
+    #[test]
+    fn collects_lang_items_on_every_target_kind() {
+        let lang_items = collect(
+            r#"
+            #[lang = "sized"]
+            trait Sized {}
+            #[lang = "panicky"]
+            fn panicky() {}
+            #[lang = "string"]
+            struct MyString {}
+            #[lang = "ordering"]
+            enum Ordering { Less, Equal, Greater }
+            #[lang = "global"]
+            static GLOBAL: i32 = 0;
+            "#,
+        );
+
+        assert!(lang_items.target("sized").unwrap().as_trait().is_some());
+        assert!(lang_items.target("panicky").unwrap().as_function().is_some());
+        assert!(lang_items.target("string").unwrap().as_struct().is_some());
+        assert!(lang_items.target("ordering").unwrap().as_enum().is_some());
+        assert!(lang_items.target("global").unwrap().as_static().is_some());
+    }
+
+    #[test]
+    fn collects_lang_items_on_enum_variants() {
+        let lang_items = collect(
+            r#"
+            enum Option<T> {
+                #[lang = "None"]
+                None,
+                #[lang = "Some"]
+                Some(T),
             }
+            "#,
+        );
+
+        match lang_items.target("None").unwrap() {
+            LangItemTarget::EnumVariant(_) => (),
+            other => panic!("expected an EnumVariant target, got {:?}", other),
         }
-        None
+        match lang_items.target("Some").unwrap() {
+            LangItemTarget::EnumVariant(_) => (),
+            other => panic!("expected an EnumVariant target, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn first_lang_item_with_a_given_name_wins() {
+        // impl blocks are visited before other declarations in
+        // `collect_lang_items_recursive`, so when both carry the same lang name the impl
+        // should win the `or_insert` race.
+        let lang_items = collect(
+            r#"
+            #[lang = "eq"]
+            trait Eq {}
+
+            struct Foo;
+            #[lang = "eq"]
+            impl Foo {}
+            "#,
+        );
+
+        assert!(lang_items.target("eq").unwrap().as_impl_block().is_some());
+    }
+
+    /// The crate in a multi-crate fixture that has a dependency edge (i.e. the one that isn't
+    /// just a leaf dependency).
+    fn dependent_crate(db: &MockDatabase) -> Crate {
+        let crate_graph = db.crate_graph();
+        let crate_id = crate_graph
+            .iter()
+            .find(|&krate| crate_graph.dependencies(krate).next().is_some())
+            .unwrap();
+        Crate { crate_id }
+    }
+
+    #[test]
+    fn lang_item_lookup_descends_into_dependencies() {
+        let (db, _file_id) = MockDatabase::with_files(
+            r#"
+            //- /main.rs crate:main deps:lib
+            fn f() {}
+            //- /lib.rs crate:lib
+            #[lang = "sized"]
+            trait Sized {}
+            "#,
+        );
+
+        // "sized" is only declared in `lib`, so finding it from `main` means the lookup
+        // actually descended into the dependency instead of stopping at the start crate.
+        let target = lang_item_lookup(&db, dependent_crate(&db), "sized");
+        assert!(target.unwrap().as_trait().is_some());
+    }
+
+    #[test]
+    fn lang_item_lookup_prefers_the_start_crate_over_a_dependency() {
+        let (db, _file_id) = MockDatabase::with_files(
+            r#"
+            //- /main.rs crate:main deps:lib
+            #[lang = "sized"]
+            trait MainSized {}
+            //- /lib.rs crate:lib
+            #[lang = "sized"]
+            trait LibSized {}
+            "#,
+        );
+
+        // Both crates declare "sized"; the start crate's own declaration should win over the
+        // dependency's, matching the original recursive search order.
+        let main_krate = dependent_crate(&db);
+        let target = lang_item_lookup(&db, main_krate, "sized").unwrap();
+        let trait_ = target.as_trait().unwrap();
+        assert_eq!(trait_.module(&db).krate(&db), Some(main_krate));
     }
 }